@@ -1,7 +1,25 @@
-use std::{io, fs, path::{PathBuf, Path}, fs::{File, read_to_string}, ffi::OsStr, time, thread, ops::Range, cmp};
-use rodio::{Decoder, OutputStream, Sink, SpatialSink, cpal::{self, traits::HostTrait, traits::DeviceTrait}};
+use std::{io, io::Write as IoWrite, fs, path::{PathBuf, Path}, fs::{File, read_to_string}, ffi::OsStr, time, thread, ops::Range, cmp, sync::mpsc::{self, Receiver}, sync::{Arc, Mutex}, net::{TcpListener, TcpStream}};
+use rodio::{Decoder, OutputStream, Sink, SpatialSink, Source, cpal::{self, traits::HostTrait, traits::DeviceTrait}};
 use clap::Parser;
 use chrono::{Local, Duration};
+use crossterm::{event::{read, Event, KeyCode, KeyEventKind}, terminal::{enable_raw_mode, disable_raw_mode}};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use roxmltree::Document;
+use lofty::{probe::Probe, file::AudioFile};
+
+
+/// Ordering applied to audio files discovered in a directory walk
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum TrackOrder {
+    /// Track-number aware ordering of the full path, so "2" sorts before "10"
+    Natural,
+    /// Track-number aware ordering of the file name only, ignoring directory nesting
+    Name,
+    /// Plain lexicographic path ordering, preserving folder grouping as found on disk
+    Path,
+    /// Random ordering, reproducible via --seed
+    Shuffle
+}
 
 /// Simple program to play a folder of songs to an audio device
 #[derive(Parser, Debug)]
@@ -25,7 +43,35 @@ struct InputArgs {
 
     /// Track range selector. Use {skip_n}:{take_n}. Either {} can be empty. {take_n} supports negative values to count from end
     #[arg(short, long, default_value_t = String::new())]
-    track_select: String
+    track_select: String,
+
+    /// Comma separated list of target disc capacities (in minutes) to report track-fitting breaks for, e.g. "60,74,80"
+    #[arg(long, default_value = "60,74,80")]
+    disc_minutes: String,
+
+    /// Serial device to pulse a track-marker byte on at each track boundary, e.g. /dev/ttyUSB0 or COM3. Requires building with --features serial
+    #[arg(long)]
+    serial_port: Option<String>,
+
+    /// Baud rate to open --serial-port with
+    #[arg(long, default_value_t = 9600)]
+    serial_baud: u32,
+
+    /// Byte value to pulse out --serial-port at each track boundary
+    #[arg(long, default_value_t = 0xFF)]
+    serial_marker: u8,
+
+    /// Ordering to apply to recursively discovered audio files
+    #[arg(long, value_enum, default_value = "natural")]
+    order: TrackOrder,
+
+    /// Seed for --order shuffle, so a randomized sequence can be reproduced for a second recording pass
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Stream decoded audio as raw PCM over TCP to a remote listener, e.g. "0.0.0.0:9988", instead of a local device
+    #[arg(long)]
+    stream: Option<String>
 }
 
 
@@ -63,7 +109,187 @@ fn parse_track_ranges(track_select_string: &String) -> std::ops::Range<Option<is
     return Range { start: lower_bound, end: Some(upper_bound) }
 }
 
-fn parse_playlist(path: &PathBuf, valid_audio_exts: &Vec<&OsStr>) -> Vec<PathBuf> {
+fn parse_disc_minutes(disc_minutes_string: &String) -> Vec<u32> {
+    disc_minutes_string
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => panic!("Disc minutes input \"{}\" could not be parsed to an integer", s)
+        })
+        .collect()
+}
+
+/// A single playable unit: a whole audio file, or one track carved out of a larger file by a CUE sheet
+#[derive(Debug, Clone)]
+enum TrackSource {
+    File(PathBuf),
+    CueTrack { file: PathBuf, title: Option<String>, start: Duration, end: Option<Duration> }
+}
+
+impl TrackSource {
+    fn file_path(&self) -> &PathBuf {
+        match self {
+            TrackSource::File(path) => path,
+            TrackSource::CueTrack { file, .. } => file
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self {
+            TrackSource::File(path) => path.file_name().unwrap().to_string_lossy().to_string(),
+            TrackSource::CueTrack { file, title, .. } => match title {
+                Some(title) => title.clone(),
+                None => file.file_name().unwrap().to_string_lossy().to_string()
+            }
+        }
+    }
+}
+
+
+fn extract_quoted(s: &str) -> Option<String> {
+    let start = s.find('"')?;
+    let end = s[start + 1..].find('"')? + start + 1;
+
+    Some(s[start + 1..end].to_string())
+}
+
+
+fn parse_cue_timestamp(s: &str) -> Option<Duration> {
+    let parts: Vec<&str> = s.trim().split(':').collect();
+
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let minutes = parts[0].parse::<i64>().ok()?;
+    let seconds = parts[1].parse::<i64>().ok()?;
+    let frames = parts[2].parse::<i64>().ok()?;
+
+    // CUE sheet INDEX timestamps are MM:SS:FF, 75 frames per second
+    Some(Duration::minutes(minutes) + Duration::seconds(seconds) + Duration::milliseconds(frames * 1000 / 75))
+}
+
+
+fn parse_cue_sheet(path: &PathBuf) -> Vec<TrackSource> {
+    // Resolve FILE entries relative to the cue sheet's own directory, not the process CWD
+    let cue_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    // (file, title, start) in file order; end-of-track is derived afterwards from the next entry
+    let mut raw_tracks: Vec<(PathBuf, Option<String>, Duration)> = Vec::new();
+
+    let mut current_file: Option<PathBuf> = None;
+    let mut pending_title: Option<String> = None;
+
+    for line in read_to_string(path).unwrap().lines() {
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("FILE ") {
+            if let Some(file_name) = extract_quoted(rest) {
+                let file_path = cue_dir.join(file_name);
+
+                if !file_path.exists() {
+                    println!("Cue sheet \"{}\" references missing file \"{}\", skipping its tracks", path.display(), file_path.display());
+                    current_file = None;
+                } else {
+                    current_file = Some(file_path);
+                }
+            }
+            continue;
+        }
+
+        if line.starts_with("TRACK ") {
+            pending_title = None;
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("TITLE ") {
+            pending_title = extract_quoted(rest);
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("INDEX 01 ") {
+            if let (Some(file_path), Some(start)) = (&current_file, parse_cue_timestamp(rest)) {
+                raw_tracks.push((file_path.clone(), pending_title.take(), start));
+            }
+            continue;
+        }
+    }
+
+    // A track's end is the next track's start in the same file, or the end of the file for the last track
+    let mut result = Vec::with_capacity(raw_tracks.len());
+
+    for (i, (file, title, start)) in raw_tracks.iter().enumerate() {
+        let end = raw_tracks.get(i + 1)
+            .filter(|(next_file, ..)| next_file == file)
+            .map(|(_, _, next_start)| *next_start);
+
+        result.push(TrackSource::CueTrack { file: file.clone(), title: title.clone(), start: *start, end });
+    }
+
+    result
+}
+
+
+/// Minimal percent-decoding for the handful of characters likely to appear in a local `file://` URI
+fn decode_file_uri(uri_path: &str) -> String {
+    let mut result = String::with_capacity(uri_path.len());
+    let mut chars = uri_path.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let hex: String = chars.by_ref().take(2).collect();
+
+            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                result.push(byte as char);
+                continue;
+            }
+        }
+
+        result.push(c);
+    }
+
+    result
+}
+
+
+/// Resolves a playlist entry (plain path or `file://` URI) relative to the playlist's own directory, not the process CWD
+fn resolve_playlist_entry(raw_entry: &str, playlist_dir: &Path) -> PathBuf {
+    let decoded = match raw_entry.strip_prefix("file://") {
+        Some(uri_path) => decode_file_uri(uri_path),
+        None => raw_entry.to_string()
+    };
+
+    let entry_path = Path::new(&decoded);
+
+    if entry_path.is_absolute() {
+        entry_path.to_path_buf()
+    }
+    else {
+        playlist_dir.join(entry_path)
+    }
+}
+
+
+fn validate_playlist_entry(file_path: &PathBuf, valid_audio_exts: &Vec<&OsStr>, raw_entry: &str) -> bool {
+    if !file_path.exists() {
+        println!("Failed to find file \"{}\" from playlist", raw_entry);
+        return false;
+    }
+
+    match file_path.extension() {
+        Some(ext) if valid_audio_exts.contains(&ext) => true,
+        _ => {
+            println!("Playlist file \"{}\" is not a supported audio type", raw_entry);
+            false
+        }
+    }
+}
+
+
+fn parse_playlist(path: &PathBuf, valid_audio_exts: &Vec<&OsStr>) -> Vec<TrackSource> {
+    let playlist_dir = path.parent().unwrap_or_else(|| Path::new("."));
     let mut result = Vec::new();
 
     for line in read_to_string(path).unwrap().lines() {
@@ -72,31 +298,165 @@ fn parse_playlist(path: &PathBuf, valid_audio_exts: &Vec<&OsStr>) -> Vec<PathBuf
             continue;
         }
 
-        // Check if file exists
-        let file_path = Path::new(line);
+        let file_path = resolve_playlist_entry(line, playlist_dir);
 
-        if !file_path.exists() {
-            println!("Failed to find file \"{}\" from playlist", line);
+        if !validate_playlist_entry(&file_path, valid_audio_exts, line) {
             continue;
         }
 
-        // Check if file is an audio file
-        if !valid_audio_exts.contains(&file_path.extension().unwrap()) {
-            println!("Playlist file \"{}\" is not a supported audio type", line);
+        // Store file and continue
+        result.push(TrackSource::File(file_path));
+    }
+
+    return result;
+}
+
+
+fn parse_xspf(path: &PathBuf, valid_audio_exts: &Vec<&OsStr>) -> Vec<TrackSource> {
+    let playlist_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let xml_text = read_to_string(path).unwrap();
+    let doc = Document::parse(&xml_text).unwrap_or_else(|err| panic!("Failed to parse XSPF playlist \"{}\": {}", path.display(), err));
+
+    let mut result = Vec::new();
+
+    for track_node in doc.descendants().filter(|node| node.has_tag_name("track")) {
+        let location = match track_node.descendants().find(|node| node.has_tag_name("location")).and_then(|node| node.text()) {
+            Some(location) => location,
+            None => continue
+        };
+
+        let file_path = resolve_playlist_entry(location, playlist_dir);
+
+        if !validate_playlist_entry(&file_path, valid_audio_exts, location) {
             continue;
         }
 
-        // Store file and continue
-        result.push(file_path.to_path_buf());
+        result.push(TrackSource::File(file_path));
     }
 
-    return result;
+    result
+}
+
+
+fn parse_pls(path: &PathBuf, valid_audio_exts: &Vec<&OsStr>) -> Vec<TrackSource> {
+    let playlist_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    // PLS orders entries by the numeric suffix on each "FileN" key, not by line order
+    let mut entries: Vec<(usize, String)> = Vec::new();
+
+    for line in read_to_string(path).unwrap().lines() {
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("File") {
+            if let Some((index_str, value)) = rest.split_once('=') {
+                if let Ok(index) = index_str.parse::<usize>() {
+                    entries.push((index, value.to_string()));
+                }
+            }
+        }
+    }
+
+    entries.sort_by_key(|(index, _)| *index);
+
+    let mut result = Vec::new();
+
+    for (_, raw_entry) in entries {
+        let file_path = resolve_playlist_entry(&raw_entry, playlist_dir);
+
+        if !validate_playlist_entry(&file_path, valid_audio_exts, &raw_entry) {
+            continue;
+        }
+
+        result.push(TrackSource::File(file_path));
+    }
+
+    result
+}
+
+
+fn collect_files_recursive(dir: &Path, results: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir.filter_map(|item| item.ok()).map(|item| item.path()).collect(),
+        Err(_) => return
+    };
+
+    entries.sort();  // Stable base order; the user-selected --order is applied afterward
+
+    for path in entries {
+        if path.is_dir() {
+            collect_files_recursive(&path, results);
+        }
+        else {
+            results.push(path);
+        }
+    }
 }
 
 
-fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isize>>) -> Vec<PathBuf> {
+/// Natural-order comparison so e.g. "track 2" sorts before "track 10" instead of after
+fn natural_cmp(a: &str, b: &str) -> cmp::Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return cmp::Ordering::Equal,
+            (None, Some(_)) => return cmp::Ordering::Less,
+            (Some(_), None) => return cmp::Ordering::Greater,
+            (Some(ac), Some(bc)) => {
+                if ac.is_ascii_digit() && bc.is_ascii_digit() {
+                    let a_num: String = std::iter::from_fn(|| a_chars.by_ref().next_if(|c| c.is_ascii_digit())).collect();
+                    let b_num: String = std::iter::from_fn(|| b_chars.by_ref().next_if(|c| c.is_ascii_digit())).collect();
+
+                    let a_val: u64 = a_num.parse().unwrap_or(0);
+                    let b_val: u64 = b_num.parse().unwrap_or(0);
+
+                    match a_val.cmp(&b_val) {
+                        cmp::Ordering::Equal => continue,
+                        other => return other
+                    }
+                }
+                else {
+                    match ac.cmp(bc) {
+                        cmp::Ordering::Equal => {
+                            a_chars.next();
+                            b_chars.next();
+                            continue;
+                        },
+                        other => return other
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+fn order_tracks(tracks: &mut Vec<TrackSource>, order: &TrackOrder, seed: Option<u64>) {
+    match order {
+        TrackOrder::Natural => tracks.sort_by(|a, b| natural_cmp(&a.file_path().to_string_lossy(), &b.file_path().to_string_lossy())),
+        TrackOrder::Name => tracks.sort_by(|a, b| natural_cmp(
+            &a.file_path().file_name().unwrap().to_string_lossy(),
+            &b.file_path().file_name().unwrap().to_string_lossy())),
+        TrackOrder::Path => tracks.sort_by(|a, b| a.file_path().cmp(b.file_path())),
+        TrackOrder::Shuffle => {
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy()
+            };
+
+            tracks.shuffle(&mut rng);
+        }
+    }
+}
+
+
+fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isize>>, order: &TrackOrder, seed: Option<u64>) -> Vec<TrackSource> {
     let valid_audio_exts = vec![OsStr::new("mp3"), OsStr::new("wav"), OsStr::new("flac")];  // Filter list for file selector
-    let playlist_exts = vec![OsStr::new("m3u8")];  // Valid playlists to load
+    let playlist_exts = vec![OsStr::new("m3u8")];  // Valid M3U8 playlists to load
+    let xspf_exts = vec![OsStr::new("xspf")];  // Valid XSPF playlists to load
+    let pls_exts = vec![OsStr::new("pls")];  // Valid PLS playlists to load
+    let cue_exts = vec![OsStr::new("cue")];  // Valid cue sheets to expand into tracks
 
     // Convert track ranges to valid counters
     let lower_bound = match track_range.start {
@@ -117,7 +477,7 @@ fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isiz
 
         // Return a single audio file if passed
         if valid_audio_exts.contains(file_ext) {
-            return vec![file_path_buf];
+            return vec![TrackSource::File(file_path_buf)];
         }
 
         // Parse a playlist if passed
@@ -125,27 +485,58 @@ fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isiz
             return parse_playlist(&file_path_buf, &valid_audio_exts);
         }
 
+        if xspf_exts.contains(file_ext) {
+            return parse_xspf(&file_path_buf, &valid_audio_exts);
+        }
+
+        if pls_exts.contains(file_ext) {
+            return parse_pls(&file_path_buf, &valid_audio_exts);
+        }
+
+        // Expand a cue sheet into its individual tracks
+        if cue_exts.contains(file_ext) {
+            return parse_cue_sheet(&file_path_buf);
+        }
+
         panic!("Unsupported extension \"{}\" for input file \"{}\"", file_ext.to_str().unwrap(), album_path)
     }
 
 
-    // Read contents of a directory
-    let mut folder_song_contents: Vec<PathBuf> = Vec::new();  // Result list
-    for item in fs::read_dir(album_path).expect("Failed to read path") {
-        if let Ok(item) = item {
-            if item.path().extension().is_some() && valid_audio_exts.contains(&item.path().extension().unwrap()) {
-                folder_song_contents.push(item.path())
+    // Recursively walk the directory (nested album/disc subfolders included), expanding any cue sheets found
+    // and skipping the raw audio files they cover
+    let mut all_files: Vec<PathBuf> = Vec::new();
+    collect_files_recursive(Path::new(album_path), &mut all_files);
+
+    let mut folder_song_contents: Vec<TrackSource> = Vec::new();  // Result list
+    let mut cue_covered_files: Vec<PathBuf> = Vec::new();
+
+    for path in &all_files {
+        if path.extension().is_some() && cue_exts.contains(&path.extension().unwrap()) {
+            for track in parse_cue_sheet(path) {
+                if let TrackSource::CueTrack { file, .. } = &track {
+                    cue_covered_files.push(file.clone());
+                }
+
+                folder_song_contents.push(track);
             }
         }
     }
 
+    for path in &all_files {
+        if path.extension().is_some() && valid_audio_exts.contains(&path.extension().unwrap()) && !cue_covered_files.contains(path) {
+            folder_song_contents.push(TrackSource::File(path.clone()))
+        }
+    }
+
+    order_tracks(&mut folder_song_contents, order, seed);
+
     // Calculate and print selection range
     if lower_bound > 0 {
         println!("Skipping {} songs", lower_bound);
     }
 
     if upper_bound > 1 {
-        println!("Taking {} songs", lower_bound);
+        println!("Taking {} songs", upper_bound);
     }
     else if upper_bound < 0 {
         let track_count = folder_song_contents.len();
@@ -155,8 +546,8 @@ fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isiz
     }
 
     // Select track range from folder results
-    let mut selected_songs: Vec<PathBuf> = Vec::new();
-    for (i, path) in folder_song_contents.iter().enumerate() {
+    let mut selected_songs: Vec<TrackSource> = Vec::new();
+    for (i, track) in folder_song_contents.into_iter().enumerate() {
         if i < lower_bound as usize {
             continue;
         }
@@ -165,7 +556,7 @@ fn get_audio_paths(album_path: &String, track_range: std::ops::Range<Option<isiz
             break;
         }
 
-        selected_songs.push(path.to_path_buf());
+        selected_songs.push(track);
     }
 
     return selected_songs;
@@ -215,43 +606,492 @@ fn get_devices() -> rodio::Device {
 }
 
 
-fn pipe_audio(output_device: &rodio::Device, output_file_path: PathBuf, stereo_pan: f32) {
+/// Playback control messages sent from the keypress-reading thread to the active track's playback loop
+#[derive(Debug, Clone, Copy)]
+enum PlayerCommand {
+    TogglePause,
+    Next,
+    Previous,
+    Stop
+}
+
+/// Result of `pipe_audio` finishing a track, either naturally or via a `PlayerCommand`
+enum TrackOutcome {
+    Finished,
+    Skipped,
+    Previous,
+    Stopped
+}
+
+/// Wraps either flavor of sink `pipe_audio` can create so playback control doesn't need to branch on pan
+enum PlaybackSink {
+    Plain(Sink),
+    Panned(SpatialSink)
+}
+
+impl PlaybackSink {
+    fn append<S: Source<Item = i16> + Send + 'static>(&self, source: S) {
+        match self {
+            PlaybackSink::Plain(sink) => sink.append(source),
+            PlaybackSink::Panned(sink) => sink.append(source)
+        }
+    }
+
+    fn pause(&self) {
+        match self {
+            PlaybackSink::Plain(sink) => sink.pause(),
+            PlaybackSink::Panned(sink) => sink.pause()
+        }
+    }
+
+    fn play(&self) {
+        match self {
+            PlaybackSink::Plain(sink) => sink.play(),
+            PlaybackSink::Panned(sink) => sink.play()
+        }
+    }
+
+    fn stop(&self) {
+        match self {
+            PlaybackSink::Plain(sink) => sink.stop(),
+            PlaybackSink::Panned(sink) => sink.stop()
+        }
+    }
+
+    fn empty(&self) -> bool {
+        match self {
+            PlaybackSink::Plain(sink) => sink.empty(),
+            PlaybackSink::Panned(sink) => sink.empty()
+        }
+    }
+}
+
+/// Keeps the sink and its pause state alive together, rather than letting `pipe_audio` block until the sink drops
+struct Player {
+    sink: PlaybackSink,
+    paused: bool
+}
+
+impl Player {
+    fn new(sink: PlaybackSink) -> Player {
+        Player { sink, paused: false }
+    }
+
+    fn toggle_pause(&mut self) {
+        if self.paused {
+            self.sink.play();
+        } else {
+            self.sink.pause();
+        }
+
+        self.paused = !self.paused;
+    }
+
+    fn stop(&mut self) {
+        self.sink.stop();
+    }
+}
+
+
+/// Fires a track-marker signal at track boundaries for recorders/microcontrollers listening on a serial line.
+/// A no-op when `--serial-port` isn't passed (or the binary wasn't built with the `serial` feature).
+enum TrackMarker {
+    Silent,
+    #[cfg(feature = "serial")]
+    Serial(Box<dyn serialport::SerialPort>, u8)
+}
+
+impl TrackMarker {
+    fn new(serial_port: &Option<String>, serial_baud: u32, serial_marker: u8) -> TrackMarker {
+        match serial_port {
+            None => TrackMarker::Silent,
+
+            #[cfg(feature = "serial")]
+            Some(port) => {
+                let port = serialport::new(port, serial_baud)
+                    .timeout(time::Duration::from_millis(100))
+                    .open()
+                    .unwrap_or_else(|err| panic!("Failed to open serial port \"{}\": {}", port, err));
+
+                TrackMarker::Serial(port, serial_marker)
+            },
+
+            #[cfg(not(feature = "serial"))]
+            Some(_) => {
+                println!("Serial port support was not compiled in, build with --features serial to enable --serial-port. Track markers disabled");
+                TrackMarker::Silent
+            }
+        }
+    }
+
+    /// Pulse the marker byte and flush immediately so timing isn't buffered
+    fn fire(&mut self) {
+        match self {
+            TrackMarker::Silent => {},
+
+            #[cfg(feature = "serial")]
+            TrackMarker::Serial(port, marker) => {
+                if let Err(err) = port.write_all(&[*marker]) {
+                    println!("Failed to write track marker to serial port: {}", err);
+                    return;
+                }
+
+                let _ = port.flush();
+            }
+        }
+    }
+}
+
+
+/// Enables terminal raw mode for single-keypress controls and restores it on drop. Keeping this tied to an RAII
+/// guard (rather than disabling inside the control thread) means the terminal is restored whether the album plays
+/// through to the end, the user hits `q`, or the process exits some other way — not only on the `Stop` keypress path.
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> Option<RawModeGuard> {
+        if enable_raw_mode().is_err() {
+            println!("Couldn't enable raw terminal mode, playback controls (pause/skip/previous/stop) are disabled");
+            return None;
+        }
+
+        Some(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+
+/// Spawns a thread that translates single keypresses into `PlayerCommand`s. Assumes raw mode has already been
+/// enabled (see `RawModeGuard`).
+fn spawn_control_thread() -> Receiver<PlayerCommand> {
+    let (command_tx, command_rx) = mpsc::channel::<PlayerCommand>();
+
+    thread::spawn(move || {
+        loop {
+            let command = match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => match key_event.code {
+                    KeyCode::Char(' ') => Some(PlayerCommand::TogglePause),
+                    KeyCode::Char('n') => Some(PlayerCommand::Next),
+                    KeyCode::Char('p') => Some(PlayerCommand::Previous),
+                    KeyCode::Char('q') => Some(PlayerCommand::Stop),
+                    _ => None
+                },
+                _ => None
+            };
+
+            if let Some(command) = command {
+                let is_stop = matches!(command, PlayerCommand::Stop);
+
+                if command_tx.send(command).is_err() || is_stop {
+                    break;
+                }
+            }
+        }
+    });
+
+    command_rx
+}
+
+
+/// Where decoded audio ends up: a local playback device, or a TCP socket streaming raw PCM to a remote listener
+enum OutputTarget {
+    Device(rodio::Device),
+    Stream(StreamTarget)
+}
+
+
+/// Holds the most recently connected `--stream` client. Tolerant of no client (writes are dropped) or a
+/// client that connects partway through an album (it just starts receiving from the next track boundary).
+struct StreamTarget {
+    client: Arc<Mutex<Option<TcpStream>>>,
+    pending_client: Arc<Mutex<Option<TcpStream>>>,  // Newly accepted client, promoted to `client` at the next track header
+    format: Mutex<(u32, u16)>  // (sample_rate, channels) of the last track sent, used to size silence during pauses
+}
+
+impl StreamTarget {
+    fn bind(addr: &str) -> StreamTarget {
+        let listener = TcpListener::bind(addr).unwrap_or_else(|err| panic!("Failed to bind --stream address \"{}\": {}", addr, err));
+        println!("Listening for a stream client on {}...", addr);
+
+        let client: Arc<Mutex<Option<TcpStream>>> = Arc::new(Mutex::new(None));
+        let pending_client: Arc<Mutex<Option<TcpStream>>> = Arc::new(Mutex::new(None));
+        let accepted_client = Arc::clone(&pending_client);
+
+        thread::spawn(move || {
+            for incoming in listener.incoming() {
+                if let Ok(stream) = incoming {
+                    println!("Stream client connected from {:?}", stream.peer_addr());
+                    *accepted_client.lock().unwrap() = Some(stream);
+                }
+            }
+        });
+
+        StreamTarget { client, pending_client, format: Mutex::new((44100, 2)) }
+    }
+
+    /// Sent at the start of every track so a client that just connected can resync rather than needing the whole album.
+    /// Encoded little-endian to match the PCM payload frames that follow.
+    fn send_track_header(&self, sample_rate: u32, channels: u16) {
+        *self.format.lock().unwrap() = (sample_rate, channels);
+
+        if let Some(new_client) = self.pending_client.lock().unwrap().take() {
+            *self.client.lock().unwrap() = Some(new_client);
+        }
+
+        self.write_frame(&sample_rate.to_le_bytes());
+        self.write_frame(&(channels as u32).to_le_bytes());
+    }
+
+    /// Keeps the remote end's clock in sync through a `--pause` gap instead of letting it sit on stale audio
+    fn send_silence(&self, duration: Duration) {
+        let (sample_rate, channels) = *self.format.lock().unwrap();
+        let sample_count = (duration.num_milliseconds() as u64 * sample_rate as u64 / 1000) as usize * channels as usize;
+
+        self.write_frame(&vec![0u8; sample_count * 2]);  // i16 samples are 2 bytes each
+    }
+
+    fn write_frame(&self, bytes: &[u8]) {
+        let mut guard = self.client.lock().unwrap();
+
+        if let Some(stream) = guard.as_mut() {
+            if stream.write_all(bytes).is_err() {
+                // Client disconnected; drop it and keep going silently until a new one connects
+                *guard = None;
+            }
+        }
+    }
+}
+
+
+/// Attenuates samples toward the un-panned channel so the balance can be countered the same way as local playback
+fn apply_stereo_pan(chunk: &[i16], channels: u16, stereo_pan: f32) -> Vec<i16> {
+    if stereo_pan.abs() <= f32::EPSILON || channels != 2 {
+        return chunk.to_vec();
+    }
+
+    let left_gain = (1.0 - stereo_pan.max(0.0)).min(1.0);
+    let right_gain = (1.0 + stereo_pan.min(0.0)).min(1.0);
+
+    chunk.chunks(2).flat_map(|frame| {
+        let left = (frame[0] as f32 * left_gain) as i16;
+        let right = if frame.len() > 1 { (frame[1] as f32 * right_gain) as i16 } else { 0 };
+        vec![left, right]
+    }).collect()
+}
+
+
+fn pipe_audio(output: &OutputTarget, track: &TrackSource, stereo_pan: f32, commands: &Receiver<PlayerCommand>) -> TrackOutcome {
+    match output {
+        OutputTarget::Device(device) => pipe_audio_to_device(device, track, stereo_pan, commands),
+        OutputTarget::Stream(stream) => pipe_audio_to_stream(stream, track, stereo_pan, commands)
+    }
+}
+
+
+fn pipe_audio_to_stream(stream: &StreamTarget, track: &TrackSource, stereo_pan: f32, commands: &Receiver<PlayerCommand>) -> TrackOutcome {
+    let output_file_path = track.file_path();
+    let file = io::BufReader::new(File::open(output_file_path).unwrap());
+    let source = Decoder::new(file).unwrap();
+
+    println!("[{}] Streaming {}... ([n] next  [p] previous  [q] stop)", Local::now().format("%I:%M %p"), track.display_name());
+
+    let sample_rate = source.sample_rate();
+    let channels = source.channels();
+    stream.send_track_header(sample_rate, channels);
+
+    let samples: Vec<i16> = match track {
+        TrackSource::File(_) => source.convert_samples().collect(),
+        TrackSource::CueTrack { start, end, .. } => {
+            let seeked = source.skip_duration(start.to_std().unwrap());
+
+            match end {
+                Some(end) => seeked.take_duration((*end - *start).to_std().unwrap()).convert_samples().collect(),
+                None => seeked.convert_samples().collect()
+            }
+        }
+    };
+
+    // Send ~100ms frames and wait out that same span between them, so the remote end receives audio at playback pace
+    // instead of all at once
+    let frame_duration = time::Duration::from_millis(100);
+    let frame_len = cmp::max(channels as usize * sample_rate as usize / 10, 1);
+
+    for chunk in samples.chunks(frame_len) {
+        let panned = apply_stereo_pan(chunk, channels, stereo_pan);
+        let bytes: Vec<u8> = panned.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        stream.write_frame(&bytes);
+
+        match commands.recv_timeout(frame_duration) {
+            Ok(PlayerCommand::Next) => return TrackOutcome::Skipped,
+            Ok(PlayerCommand::Previous) => return TrackOutcome::Previous,
+            Ok(PlayerCommand::Stop) => return TrackOutcome::Stopped,
+            _ => continue
+        }
+    }
+
+    TrackOutcome::Finished
+}
+
+
+fn pipe_audio_to_device(output_device: &rodio::Device, track: &TrackSource, stereo_pan: f32, commands: &Receiver<PlayerCommand>) -> TrackOutcome {
     // Get a output stream handle to the output physical sound device
     let (_stream, stream_handle) = OutputStream::try_from_device(&output_device).unwrap();
-    
+
     // Load a sound from a file, using a path relative to Cargo.toml
-    let file = io::BufReader::new(File::open(output_file_path.clone()).unwrap());
+    let output_file_path = track.file_path();
+    let file = io::BufReader::new(File::open(output_file_path).unwrap());
     // Decode that sound file into a source
     let source = Decoder::new(file).unwrap();
-    
+
     // Play audio and wait
-    println!("[{}] Playing {}...", Local::now().format("%I:%M %p"), output_file_path.file_name().unwrap().to_string_lossy());
-    if stereo_pan.abs() <= f32::EPSILON {
-        // Un-panned audio
-        let sink = Sink::try_new(&stream_handle).unwrap();
+    println!("[{}] Playing {}... ([space] pause/resume  [n] next  [p] previous  [q] stop)", Local::now().format("%I:%M %p"), track.display_name());
 
-        sink.append(source);
-        sink.sleep_until_end();
+    let sink = if stereo_pan.abs() <= f32::EPSILON {
+        // Un-panned audio
+        PlaybackSink::Plain(Sink::try_new(&stream_handle).unwrap())
     }
     else {
         // Panned audio
         let pan_postition = [stereo_pan, 0.0, 0.0];
 
-        let pan_sink = SpatialSink::try_new(&stream_handle, 
+        PlaybackSink::Panned(SpatialSink::try_new(&stream_handle,
             pan_postition,
-            [-1.0, 0.0, 0.0], 
+            [-1.0, 0.0, 0.0],
             [1.0, 0.0, 0.0])
-            .unwrap();
+            .unwrap())
+    };
+
+    // A plain file plays start to finish; a cue track seeks to its start and stops at its end (or end-of-file)
+    match track {
+        TrackSource::File(_) => sink.append(source),
+        TrackSource::CueTrack { start, end, .. } => {
+            let seeked = source.skip_duration(start.to_std().unwrap());
+
+            match end {
+                Some(end) => sink.append(seeked.take_duration((*end - *start).to_std().unwrap())),
+                None => sink.append(seeked)
+            }
+        }
+    }
+
+    let mut player = Player::new(sink);
+
+    loop {
+        if player.sink.empty() {
+            return TrackOutcome::Finished;
+        }
+
+        match commands.recv_timeout(time::Duration::from_millis(100)) {
+            Ok(PlayerCommand::TogglePause) => player.toggle_pause(),
+            Ok(PlayerCommand::Next) => {
+                player.stop();
+                return TrackOutcome::Skipped;
+            },
+            Ok(PlayerCommand::Previous) => {
+                player.stop();
+                return TrackOutcome::Previous;
+            },
+            Ok(PlayerCommand::Stop) => {
+                player.stop();
+                return TrackOutcome::Stopped;
+            },
+            Err(_) => continue
+        }
+    }
+}
+
 
-            pan_sink.append(source);
-            pan_sink.sleep_until_end();
+fn println_end_time(run_time: Duration) {
+    let endtime_delta = Local::now() + run_time;
+    println!("Will end at [{}] for [+{}m]", endtime_delta.format("%I:%M %p"), run_time.num_minutes());
+}
+
+
+// Read duration from container/tag metadata via lofty rather than rodio's decoder, since rodio's
+// `Source::total_duration()` returns None for MP3 (our most common input format), which silently
+// collapsed the disc-fitting report to ~0 minutes for any MP3 album.
+fn decode_total_duration(song_path: &PathBuf) -> Duration {
+    match Probe::open(song_path).and_then(|probe| probe.read()) {
+        Ok(tagged_file) => Duration::from_std(tagged_file.properties().duration()).unwrap(),
+        Err(err) => {
+            println!("Couldn't determine duration of \"{}\" ({}), treating it as 0 seconds", song_path.display(), err);
+            Duration::zero()
+        }
     }
 }
 
 
-fn println_end_time(duration: i64) {
-    let endtime_delta = Local::now() + Duration::minutes(duration);
-    println!("Will end at [{}] for [+{}m]", endtime_delta.format("%I:%M %p"), duration);
+fn get_song_duration(track: &TrackSource) -> Duration {
+    match track {
+        TrackSource::File(path) => decode_total_duration(path),
+        TrackSource::CueTrack { file, start, end, .. } => match end {
+            Some(end) => *end - *start,
+            None => decode_total_duration(file) - *start
+        }
+    }
+}
+
+
+fn get_total_run_time(song_durations: &Vec<Duration>, pause: f32, delay: f32) -> Duration {
+    let pause_duration = Duration::milliseconds((pause * 1000.0) as i64);
+    let delay_duration = Duration::milliseconds((delay * 1000.0) as i64);
+
+    let songs_total: Duration = song_durations.iter().fold(Duration::zero(), |acc, d| acc + *d);
+    let pause_total = pause_duration * cmp::max(song_durations.len() as i32 - 1, 0);
+
+    songs_total + pause_total + delay_duration
+}
+
+
+fn print_disc_fitting_report(song_paths: &Vec<TrackSource>, song_durations: &Vec<Duration>, pause: f32, disc_minutes: &Vec<u32>) {
+    let pause_duration = Duration::milliseconds((pause * 1000.0) as i64);
+
+    for capacity_minutes in disc_minutes {
+        let capacity = Duration::minutes(*capacity_minutes as i64);
+        println!("\nFitting tracks to a {}-minute disc:", capacity_minutes);
+
+        let mut disc_number = 1;
+        let mut disc_start = 0;
+        let mut running_total = Duration::zero();
+
+        for (i, duration) in song_durations.iter().enumerate() {
+            // A single track longer than the disc can never fit; warn and let it occupy its own disc
+            if *duration > capacity {
+                if running_total > Duration::zero() {
+                    println!(" Disc {}: tracks {}-{}", disc_number, disc_start + 1, i);
+                    disc_number += 1;
+                }
+
+                println!(" Warning: track {} (\"{}\") is longer than a {}-minute disc by itself", i + 1, song_paths[i].display_name(), capacity_minutes);
+                println!(" Disc {}: track {}", disc_number, i + 1);
+                disc_number += 1;
+                disc_start = i + 1;
+                running_total = Duration::zero();
+                continue;
+            }
+
+            let addition = if running_total > Duration::zero() { pause_duration + *duration } else { *duration };
+
+            if running_total + addition > capacity {
+                println!(" Disc {}: tracks {}-{}", disc_number, disc_start + 1, i);
+                disc_number += 1;
+                disc_start = i;
+                running_total = *duration;
+            }
+            else {
+                running_total = running_total + addition;
+            }
+        }
+
+        if disc_start < song_durations.len() {
+            println!(" Disc {}: tracks {}-{}", disc_number, disc_start + 1, song_durations.len());
+        }
+    }
 }
 
 
@@ -263,7 +1103,7 @@ fn main() {
 
     // Get album song paths
     println!("Album contents to be played:");
-    let song_paths = &get_audio_paths(&args.input_path, track_range);
+    let song_paths = &get_audio_paths(&args.input_path, track_range, &args.order, args.seed);
 
     for path in song_paths {
         println!("{:?}", path);
@@ -273,9 +1113,19 @@ fn main() {
         println!("{} second delay in-between tracks", args.pause);
     }
 
-    // Get audio device
+    // Get audio device, unless output is being streamed to a remote listener instead
     println!("");
-    let output_device = get_devices();
+    let output_target = match &args.stream {
+        Some(addr) => OutputTarget::Stream(StreamTarget::bind(addr)),
+        None => OutputTarget::Device(get_devices())
+    };
+
+    // Project the finish time before the start delay elapses, since the projection already includes it
+    let disc_minutes = parse_disc_minutes(&args.disc_minutes);
+    let song_durations: Vec<Duration> = song_paths.iter().map(get_song_duration).collect();
+
+    println_end_time(get_total_run_time(&song_durations, args.pause, args.delay));
+    print_disc_fitting_report(song_paths, &song_durations, args.pause, &disc_minutes);
 
     println!("");
     // Apply playback pause
@@ -287,23 +1137,42 @@ fn main() {
         thread::sleep(n_seconds);
     }
 
-    println_end_time(32);
-    println_end_time(45);
-    println_end_time(74);
-    println_end_time(80);
+    // Play songs, watching for playback control keypresses in-between
+    let _raw_mode_guard = RawModeGuard::enable();
+    let control_commands = spawn_control_thread();
+    let mut track_marker = TrackMarker::new(&args.serial_port, args.serial_baud, args.serial_marker);
+
+    let mut song_index = 0;
+    while song_index < song_paths.len() {
+        // Pulse the marker right as a new track starts so rigs listening on the serial line can fire a record mark
+        track_marker.fire();
+        let outcome = pipe_audio(&output_target, &song_paths[song_index], args.stereo_pan, &control_commands);
+
+        match outcome {
+            TrackOutcome::Stopped => {
+                println!("Playback stopped");
+                break;
+            },
+            TrackOutcome::Previous => {
+                song_index = song_index.saturating_sub(1);
+                continue;
+            },
+            TrackOutcome::Finished | TrackOutcome::Skipped => {
+                // Apply audio playback pause delay
+                if args.pause > 0.0 {
+                    println!("Waiting {} seconds...", args.pause);
 
-    // Play songs
-    for song in song_paths {
-        pipe_audio(&output_device, song.to_path_buf(), args.stereo_pan);
+                    if let OutputTarget::Stream(stream) = &output_target {
+                        stream.send_silence(Duration::milliseconds((args.pause * 1000.0) as i64));
+                    }
 
-        // Apply audio playback pause delay
-        if args.pause > 0.0 {
-            println!("Waiting {} seconds...", args.pause);
+                    let n_seconds = time::Duration::from_secs_f32(args.pause.into());
+                    thread::sleep(n_seconds);
+                }
 
-            let n_seconds = time::Duration::from_secs_f32(args.pause.into());
-            thread::sleep(n_seconds);
+                song_index += 1;
+            }
         }
-
     }
 
     println!("[{}] Done!", Local::now().format("%H:%M"));